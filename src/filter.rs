@@ -0,0 +1,83 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterValue {
+  Str(String),
+  Num(f64)
+}
+
+#[derive(Debug, Clone)]
+pub enum FilterExpr {
+  Eq(String, FilterValue),
+  In(String, Vec<FilterValue>),
+  Gte(String, f64),
+  Lte(String, f64),
+  And(Vec<FilterExpr>),
+  Or(Vec<FilterExpr>)
+}
+
+fn parse_value(raw: &str) -> FilterValue {
+  let trimmed = raw.trim().trim_matches('"');
+
+  if let Ok(num) = trimmed.parse::<f64>() {
+    return FilterValue::Num(num);
+  }
+
+  return FilterValue::Str(trimmed.to_string());
+}
+
+fn parse_clause(clause: &str) -> Option<FilterExpr> {
+  let clause = clause.trim();
+
+  if let Some(idx) = clause.find(">=") {
+    let field = clause[..idx].trim().to_string();
+    let value = clause[idx + 2..].trim().parse::<f64>().ok()?;
+    return Some(FilterExpr::Gte(field, value));
+  }
+
+  if let Some(idx) = clause.find("<=") {
+    let field = clause[..idx].trim().to_string();
+    let value = clause[idx + 2..].trim().parse::<f64>().ok()?;
+    return Some(FilterExpr::Lte(field, value));
+  }
+
+  if let Some(idx) = clause.to_uppercase().find(" IN ") {
+    let field = clause[..idx].trim().to_string();
+    let rest = clause[idx + 4..].trim();
+    let inner = rest.trim_start_matches('[').trim_end_matches(']');
+    let values: Vec<FilterValue> = inner.split(',').map(|v| parse_value(v)).collect();
+    return Some(FilterExpr::In(field, values));
+  }
+
+  if let Some(idx) = clause.find('=') {
+    let field = clause[..idx].trim().to_string();
+    let value = parse_value(&clause[idx + 1..]);
+    return Some(FilterExpr::Eq(field, value));
+  }
+
+  return None;
+}
+
+pub fn parse_filter(expr: &str) -> Option<FilterExpr> {
+  let or_groups: Vec<&str> = expr.split(" OR ").collect();
+  let mut or_exprs: Vec<FilterExpr> = Vec::new();
+
+  for group in or_groups {
+    let and_clauses: Vec<&str> = group.split(" AND ").collect();
+    let mut and_exprs: Vec<FilterExpr> = Vec::new();
+
+    for clause in and_clauses {
+      and_exprs.push(parse_clause(clause)?);
+    }
+
+    if and_exprs.len() == 1 {
+      or_exprs.push(and_exprs.into_iter().next().unwrap());
+    } else {
+      or_exprs.push(FilterExpr::And(and_exprs));
+    }
+  }
+
+  if or_exprs.len() == 1 {
+    return Some(or_exprs.into_iter().next().unwrap());
+  }
+
+  return Some(FilterExpr::Or(or_exprs));
+}
@@ -1,8 +1,14 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use serde_json::{Value};
 use sublime_fuzzy::{FuzzySearch};
 
-use crate::lp::{gramify, clean_words};
+use crate::lp::{gramify, clean_words, typo_candidates, edit_distance};
+use crate::query::{Operation, parse_query};
+use crate::filter::{FilterExpr, FilterValue};
+use crate::ranking::{RankingRule, SortDirection};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 fn parse_json(datastr: String) -> Value {
   return serde_json::from_str(&datastr).unwrap();
@@ -11,27 +17,74 @@ fn parse_json(datastr: String) -> Value {
 pub struct Index {
   pub id_counter: u32,
   pub items: HashMap<u32, String>,
-  pub token_scoring: HashMap<String, Vec<(u32, u8)>>,
+  pub token_scoring: HashMap<String, Vec<(u32, u32)>>,
+  pub word_keys: HashSet<String>,
+  pub doc_lengths: HashMap<u32, f64>,
+  pub avg_doc_length: f64,
   pub id_map: HashMap<String, u32>,
   pub fields: Vec<String>,
-  pub query_times: VecDeque<(u64, u64)>
+  pub query_times: VecDeque<(u64, u64)>,
+  pub filterable_fields: Vec<String>,
+  pub sortable_fields: Vec<String>,
+  pub filter_values: HashMap<String, HashMap<String, Vec<u32>>>,
+  pub sortable_values: HashMap<String, Vec<(u32, f64)>>,
+  pub rrf_k: f32,
+  pub token_ranking_enabled: bool,
+  pub fuzzy_ranking_enabled: bool,
+  pub typo_threshold_short: usize,
+  pub typo_threshold_medium: usize,
+  pub typo_threshold_long: usize,
+  pub max_typos: usize,
+  pub k1: f32,
+  pub b: f32,
+  pub word_weight: f32,
+  pub gram_weight: f32,
+  pub ranking_rules: Vec<RankingRule>
 }
 
 pub fn clear(index: &mut Index) {
   index.id_counter = 0;
   index.items = HashMap::new();
   index.token_scoring = HashMap::new();
+  index.word_keys = HashSet::new();
+  index.doc_lengths = HashMap::new();
+  index.avg_doc_length = 0.0;
   index.id_map = HashMap::new();
+  index.filter_values = HashMap::new();
+  index.sortable_values = HashMap::new();
 }
 
 pub fn create(fields: Vec<String>) -> Index {
+  create_with_filters(fields, Vec::new(), Vec::new())
+}
+
+pub fn create_with_filters(fields: Vec<String>, filterable_fields: Vec<String>, sortable_fields: Vec<String>) -> Index {
   Index {
     id_counter: 0,
     items: HashMap::new(),
     token_scoring: HashMap::new(),
+    word_keys: HashSet::new(),
+    doc_lengths: HashMap::new(),
+    avg_doc_length: 0.0,
     id_map: HashMap::new(),
     fields,
-    query_times: VecDeque::new()
+    query_times: VecDeque::new(),
+    filterable_fields,
+    sortable_fields,
+    filter_values: HashMap::new(),
+    sortable_values: HashMap::new(),
+    rrf_k: 60.0,
+    token_ranking_enabled: true,
+    fuzzy_ranking_enabled: true,
+    typo_threshold_short: 0,
+    typo_threshold_medium: 1,
+    typo_threshold_long: 2,
+    max_typos: 2,
+    k1: 1.2,
+    b: 0.75,
+    word_weight: 3.0,
+    gram_weight: 1.0,
+    ranking_rules: Vec::new()
   }
 }
 
@@ -84,6 +137,11 @@ pub fn remove(index: &mut Index, id: String) -> bool {
   }
 
   index.token_scoring.retain(|_, x| x.len() > 0);
+  let live_word_keys: HashSet<String> = index.token_scoring.keys().cloned().collect();
+  index.word_keys.retain(|w| live_word_keys.contains(w));
+  remove_filter_fields(index, iid);
+  index.doc_lengths.remove(&iid);
+  recompute_avg_doc_length(index);
 
   return true;
 }
@@ -98,6 +156,11 @@ pub fn update(index: &mut Index, obj: Value) {
   }
 
   index.token_scoring.retain(|_, x| x.len() > 0);
+  let live_word_keys: HashSet<String> = index.token_scoring.keys().cloned().collect();
+  index.word_keys.retain(|w| live_word_keys.contains(w));
+  remove_filter_fields(index, iid);
+  index_filter_fields(index, iid, &obj);
+  index.doc_lengths.remove(&iid);
 
   index.items.insert(iid as u32, obj.to_string());
   index_item(index, iid, token_str.trim().to_string());
@@ -107,6 +170,8 @@ pub fn add_object(index: &mut Index, obj: Value) {
   let token_str = extract_fields(&obj, &index.fields);
   let id = &obj["_id"].as_str().unwrap();
 
+  index_filter_fields(index, index.id_counter, &obj);
+
   add(
     index,
     id.to_string(),
@@ -119,93 +184,586 @@ fn add(index: &mut Index, id: String, obj: String, to_tokenize: String) {
   let iid = index.id_counter;
   index.id_map.insert(id, iid);
   index.id_counter += 1;
-  
+
   index.items.insert(iid as u32, obj);
   index_item(index, iid, to_tokenize);
 }
 
+#[cfg(feature = "parallel")]
+pub fn add_objects(index: &mut Index, objects: Vec<Value>) {
+  let base_iid = index.id_counter;
+  let fields = index.fields.clone();
+  let filterable_fields = index.filterable_fields.clone();
+  let sortable_fields = index.sortable_fields.clone();
+
+  let partials: Vec<(u32, String, String, HashMap<String, (u32, bool)>, f64, Vec<(String, String)>, Vec<(String, f64)>)> = objects
+    .par_iter()
+    .enumerate()
+    .map(|(offset, obj)| {
+      let iid = base_iid + offset as u32;
+      let token_str = extract_fields(obj, &fields).trim().to_string();
+      let id = obj["_id"].as_str().unwrap().to_string();
+
+      let grams = gramify(token_str.clone());
+      let words = clean_words(token_str.clone());
+      let doc_length = (grams.len() + words.len()) as f64;
+
+      let mut postings: HashMap<String, (u32, bool)> = HashMap::new();
+
+      for gram in grams.iter() {
+        let entry = postings.entry(gram.clone()).or_insert((0, false));
+        entry.0 += 1;
+      }
+
+      for word in words.iter() {
+        let entry = postings.entry(word.clone()).or_insert((0, false));
+        entry.0 += 1;
+        entry.1 = true;
+      }
+
+      let mut filters: Vec<(String, String)> = Vec::new();
+      for field in filterable_fields.iter() {
+        if let Some(key) = filter_value_key(&obj[field]) {
+          filters.push((field.clone(), key));
+        }
+      }
+
+      let mut sortables: Vec<(String, f64)> = Vec::new();
+      for field in sortable_fields.iter() {
+        if obj[field].is_number() {
+          sortables.push((field.clone(), obj[field].as_f64().unwrap()));
+        }
+      }
+
+      (iid, id, obj.to_string(), postings, doc_length, filters, sortables)
+    })
+    .collect();
+
+  let object_count = objects.len();
+
+  for (iid, id, obj_str, postings, doc_length, filters, sortables) in partials {
+    index.id_map.insert(id, iid);
+    index.items.insert(iid, obj_str);
+    index.doc_lengths.insert(iid, doc_length);
+
+    for (key, (count, is_word)) in postings {
+      if is_word {
+        index.word_keys.insert(key.clone());
+      }
+      index.token_scoring.entry(key).or_insert_with(Vec::new).push((iid, count));
+    }
+
+    for (field, key) in filters {
+      index.filter_values.entry(field).or_insert_with(HashMap::new).entry(key).or_insert_with(Vec::new).push(iid);
+    }
+
+    for (field, value) in sortables {
+      index.sortable_values.entry(field).or_insert_with(Vec::new).push((iid, value));
+    }
+  }
+
+  index.id_counter = base_iid + object_count as u32;
+  recompute_avg_doc_length(index);
+}
+
+#[cfg(not(feature = "parallel"))]
+pub fn add_objects(index: &mut Index, objects: Vec<Value>) {
+  for obj in objects {
+    add_object(index, obj);
+  }
+}
+
+fn filter_value_key(value: &Value) -> Option<String> {
+  if value.is_string() {
+    return Some(value.as_str().unwrap().to_string());
+  }
+  if value.is_number() {
+    return Some(value.as_f64().unwrap().to_string());
+  }
+  if value.is_boolean() {
+    return Some(value.as_bool().unwrap().to_string());
+  }
+  return None;
+}
+
+fn index_filter_fields(index: &mut Index, iid: u32, obj: &Value) {
+  for field in index.filterable_fields.clone().iter() {
+    if let Some(key) = filter_value_key(&obj[field]) {
+      index.filter_values
+        .entry(field.clone())
+        .or_insert_with(HashMap::new)
+        .entry(key)
+        .or_insert_with(Vec::new)
+        .push(iid);
+    }
+  }
+
+  for field in index.sortable_fields.clone().iter() {
+    if obj[field].is_number() {
+      index.sortable_values
+        .entry(field.clone())
+        .or_insert_with(Vec::new)
+        .push((iid, obj[field].as_f64().unwrap()));
+    }
+  }
+}
+
+fn remove_filter_fields(index: &mut Index, iid: u32) {
+  for value_map in index.filter_values.values_mut() {
+    for ids in value_map.values_mut() {
+      ids.retain(|x| *x != iid);
+    }
+    value_map.retain(|_, ids| ids.len() > 0);
+  }
+
+  for values in index.sortable_values.values_mut() {
+    values.retain(|x| x.0 != iid);
+  }
+}
+
+fn recompute_avg_doc_length(index: &mut Index) {
+  if index.doc_lengths.len() == 0 {
+    index.avg_doc_length = 0.0;
+    return;
+  }
+
+  let total: f64 = index.doc_lengths.values().sum();
+  index.avg_doc_length = total / index.doc_lengths.len() as f64;
+}
+
 fn index_item(index: &mut Index, iid: u32, to_tokenize: String) {
-  let mut grams = gramify(to_tokenize.to_string());
-  grams.sort_unstable();
-  grams.dedup();
+  let grams = gramify(to_tokenize.to_string());
+  let words = clean_words(to_tokenize.to_string());
 
-  for gram in grams {
-    if !index.token_scoring.contains_key(&gram.clone()) {
-      index.token_scoring.insert(gram.to_string(), vec![(iid as u32, 1)]);
-    } else {
-      index.token_scoring.get_mut(&gram).unwrap().push((iid as u32, 1));
+  let mut counts: HashMap<String, u32> = HashMap::new();
+  for gram in grams.iter() {
+    *counts.entry(gram.clone()).or_insert(0) += 1;
+  }
+  for word in words.iter() {
+    *counts.entry(word.clone()).or_insert(0) += 1;
+    index.word_keys.insert(word.clone());
+  }
+
+  for (key, count) in counts {
+    index.token_scoring.entry(key).or_insert_with(Vec::new).push((iid, count));
+  }
+
+  index.doc_lengths.insert(iid, (grams.len() + words.len()) as f64);
+  recompute_avg_doc_length(index);
+}
+
+fn super_string_for(index: &Index, id: u32) -> String {
+  let item = index.items.get(&id).unwrap().clone();
+  let value = parse_json(item);
+  return extract_fields(&value, &index.fields);
+}
+
+fn cleaned_doc_text(index: &Index, id: u32) -> String {
+  return clean_words(super_string_for(index, id)).join(" ");
+}
+
+fn typo_budget(index: &Index, word_len: usize) -> usize {
+  let threshold = if word_len <= 4 {
+    index.typo_threshold_short
+  } else if word_len <= 8 {
+    index.typo_threshold_medium
+  } else {
+    index.typo_threshold_long
+  };
+
+  return threshold.min(index.max_typos);
+}
+
+fn field_weight_for(index: &Index, term: &str) -> f32 {
+  if index.word_keys.contains(term) {
+    index.word_weight
+  } else {
+    index.gram_weight
+  }
+}
+
+fn bm25_idf(index: &Index, df: usize) -> f32 {
+  let n = index.items.len() as f32;
+  let df = df as f32;
+  return (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+}
+
+fn bm25_scores_for_postings(index: &Index, postings: &Vec<(u32, u32)>, field_weight: f32) -> HashMap<u32, f32> {
+  let mut scores: HashMap<u32, f32> = HashMap::new();
+  let idf = bm25_idf(index, postings.len());
+  let avg_length = if index.avg_doc_length > 0.0 { index.avg_doc_length as f32 } else { 1.0 };
+
+  for (id, tf) in postings.iter() {
+    let tf = *tf as f32;
+    let doc_length = index.doc_lengths.get(id).cloned().unwrap_or(index.avg_doc_length) as f32;
+
+    let numerator = tf * (index.k1 + 1.0);
+    let denominator = tf + index.k1 * (1.0 - index.b + index.b * (doc_length / avg_length));
+
+    scores.insert(*id, idf * (numerator / denominator) * field_weight);
+  }
+
+  return scores;
+}
+
+fn term_scores(index: &Index, term: &str) -> HashMap<u32, f32> {
+  if let Some(postings) = index.token_scoring.get(term) {
+    return bm25_scores_for_postings(index, postings, field_weight_for(index, term));
+  }
+
+  let mut scores: HashMap<u32, f32> = HashMap::new();
+
+  let budget = typo_budget(index, term.chars().count());
+  if budget == 0 {
+    return scores;
+  }
+
+  for (key, distance) in typo_candidates(term, index.word_keys.iter(), budget) {
+    let postings = index.token_scoring.get(key).unwrap();
+    let weight = field_weight_for(index, key) / (1.0 + distance as f32);
+
+    for (id, score) in bm25_scores_for_postings(index, postings, weight) {
+      *scores.entry(id).or_insert(0.0) += score;
+    }
+  }
+
+  return scores;
+}
+
+fn intersect_scores(a: HashMap<u32, f32>, b: &HashMap<u32, f32>) -> HashMap<u32, f32> {
+  let mut result: HashMap<u32, f32> = HashMap::new();
+
+  for (id, score) in a.iter() {
+    if let Some(other_score) = b.get(id) {
+      result.insert(*id, score + other_score);
     }
   }
 
-  let mut words = clean_words(to_tokenize.to_string());
-  words.sort_unstable();
-  words.dedup();
+  return result;
+}
+
+fn evaluate_operation(index: &Index, op: &Operation) -> HashMap<u32, f32> {
+  match op {
+    Operation::Term(term) => term_scores(index, term),
+    Operation::Phrase(words) => {
+      let mut candidates: Option<HashMap<u32, f32>> = None;
+
+      for word in words.iter() {
+        let word_scores = term_scores(index, word);
+        candidates = Some(match candidates {
+          None => word_scores,
+          Some(prev) => intersect_scores(prev, &word_scores)
+        });
+      }
+
+      let phrase = words.join(" ");
+      let mut result: HashMap<u32, f32> = HashMap::new();
+
+      if let Some(candidates) = candidates {
+        for (id, score) in candidates {
+          if cleaned_doc_text(index, id).contains(&phrase) {
+            result.insert(id, score);
+          }
+        }
+      }
+
+      return result;
+    },
+    Operation::And(ops) => {
+      let mut result: Option<HashMap<u32, f32>> = None;
+
+      for sub_op in ops.iter() {
+        let sub_scores = evaluate_operation(index, sub_op);
+        result = Some(match result {
+          None => sub_scores,
+          Some(prev) => intersect_scores(prev, &sub_scores)
+        });
+      }
+
+      return result.unwrap_or_else(HashMap::new);
+    },
+    Operation::Or(ops) => {
+      let mut result: HashMap<u32, f32> = HashMap::new();
 
-  for word in words {
-    if !index.token_scoring.contains_key(&word.clone()) {
-      index.token_scoring.insert(word.to_string(), vec![(iid as u32, 50)]);
+      for sub_op in ops.iter() {
+        for (id, score) in evaluate_operation(index, sub_op) {
+          *result.entry(id).or_insert(0.0) += score;
+        }
+      }
+
+      return result;
+    },
+    Operation::Not(sub_op) => {
+      let excluded = evaluate_operation(index, sub_op);
+      let mut result: HashMap<u32, f32> = HashMap::new();
+
+      for id in index.items.keys() {
+        if !excluded.contains_key(id) {
+          result.insert(*id, 0.0);
+        }
+      }
+
+      return result;
+    }
+  }
+}
+
+fn group_consecutive<K: PartialEq + Copy>(ids: Vec<(u32, K)>) -> Vec<Vec<u32>> {
+  let mut buckets: Vec<Vec<u32>> = Vec::new();
+  let mut last_key: Option<K> = None;
+
+  for (id, key) in ids {
+    if last_key.map_or(false, |lk| lk == key) {
+      buckets.last_mut().unwrap().push(id);
     } else {
-      index.token_scoring.get_mut(&word).unwrap().push((iid as u32, 50));
+      buckets.push(vec![id]);
+      last_key = Some(key);
     }
   }
+
+  return buckets;
 }
 
-fn get_key_score_list(index: &Index, query: String) -> Vec<(u32, f32)> {
-  let mut scores: HashMap<u32, f32> = HashMap::new();
-  let mut query_tokens = gramify(query.clone());
-  
-  for word in clean_words(query.clone()) {
-    query_tokens.push(word.clone());
+fn bucket_sort_desc<K: PartialOrd + PartialEq + Copy>(mut scored: Vec<(u32, K)>) -> Vec<Vec<u32>> {
+  scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+  return group_consecutive(scored);
+}
+
+fn bucket_sort_asc<K: PartialOrd + PartialEq + Copy>(mut scored: Vec<(u32, K)>) -> Vec<Vec<u32>> {
+  scored.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+  return group_consecutive(scored);
+}
+
+fn min_span(doc_words: &Vec<String>, query_words: &Vec<String>) -> i64 {
+  let mut unique_query_words: Vec<String> = Vec::new();
+  for word in query_words {
+    if !unique_query_words.contains(word) {
+      unique_query_words.push(word.clone());
+    }
+  }
+
+  let mut positions: Vec<(usize, usize)> = Vec::new();
+
+  for (pos, word) in doc_words.iter().enumerate() {
+    if let Some(query_index) = unique_query_words.iter().position(|w| w == word) {
+      positions.push((query_index, pos));
+    }
+  }
+
+  let required: HashSet<usize> = (0..unique_query_words.len()).collect();
+  if positions.len() == 0 || required.len() == 0 {
+    return i64::MAX;
   }
 
-  for token in query_tokens.iter() {
-    if index.token_scoring.contains_key(&token.clone()) {
-      let ids = index.token_scoring.get(token).unwrap();
-      
-      for id in ids.iter() {
-        *scores.entry(id.0).or_insert(0.0) += id.1 as f32;
+  positions.sort_by_key(|x| x.1);
+
+  let mut counts: HashMap<usize, usize> = HashMap::new();
+  let mut left = 0;
+  let mut best = i64::MAX;
+
+  for right in 0..positions.len() {
+    *counts.entry(positions[right].0).or_insert(0) += 1;
+
+    while counts.len() == required.len() {
+      let span = (positions[right].1 - positions[left].1) as i64;
+      if span < best {
+        best = span;
+      }
+
+      let left_word = positions[left].0;
+      let count = counts.get_mut(&left_word).unwrap();
+      *count -= 1;
+      if *count == 0 {
+        counts.remove(&left_word);
+      }
+      left += 1;
+    }
+  }
+
+  return best;
+}
+
+fn apply_rule_to_bucket(index: &Index, rule: &RankingRule, query: &str, query_words: &Vec<String>, bucket: Vec<u32>) -> Vec<Vec<u32>> {
+  match rule {
+    RankingRule::Words => {
+      let scored: Vec<(u32, i64)> = bucket.iter().map(|id| {
+        let doc_words: HashSet<String> = clean_words(super_string_for(index, *id)).into_iter().collect();
+        let matched = query_words.iter().filter(|w| doc_words.contains(*w)).count() as i64;
+        (*id, matched)
+      }).collect();
+
+      return bucket_sort_desc(scored);
+    },
+    RankingRule::Typo => {
+      let scored: Vec<(u32, i64)> = bucket.iter().map(|id| {
+        let doc_words = clean_words(super_string_for(index, *id));
+        let total: usize = query_words.iter().map(|query_word| {
+          doc_words.iter().map(|doc_word| edit_distance(query_word, doc_word)).min().unwrap_or(index.max_typos + 1)
+        }).sum();
+        (*id, total as i64)
+      }).collect();
+
+      return bucket_sort_asc(scored);
+    },
+    RankingRule::Proximity => {
+      let scored: Vec<(u32, i64)> = bucket.iter().map(|id| {
+        let doc_words = clean_words(super_string_for(index, *id));
+        (*id, min_span(&doc_words, query_words))
+      }).collect();
+
+      return bucket_sort_asc(scored);
+    },
+    RankingRule::Fuzzy => {
+      let mut scored: Vec<(u32, f32)> = bucket.iter().map(|id| {
+        let super_string = super_string_for(index, *id);
+        let mut search = FuzzySearch::new(query, &super_string, true);
+        let score = search.best_match().map(|m| m.score() as f32).unwrap_or(0.0);
+        (*id, score)
+      }).collect();
+
+      scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+      return scored.into_iter().map(|(id, _)| vec![id]).collect();
+    },
+    RankingRule::FieldWeight(field) => {
+      let mut matches: Vec<u32> = Vec::new();
+      let mut rest: Vec<u32> = Vec::new();
+      let query_lower = query.to_lowercase();
+
+      for id in bucket.iter() {
+        let item = index.items.get(id).unwrap().clone();
+        let value = parse_json(item);
+        let field_value = value[field].as_str().unwrap_or("").to_lowercase();
+
+        if field_value.contains(&query_lower) {
+          matches.push(*id);
+        } else {
+          rest.push(*id);
+        }
+      }
+
+      let mut buckets: Vec<Vec<u32>> = Vec::new();
+      if matches.len() > 0 {
+        buckets.push(matches);
       }
+      if rest.len() > 0 {
+        buckets.push(rest);
+      }
+
+      return buckets;
+    },
+    RankingRule::Sort(field, direction) => {
+      let values: HashMap<u32, f64> = index.sortable_values
+        .get(field)
+        .map(|v| v.iter().cloned().collect())
+        .unwrap_or_else(HashMap::new);
+
+      let scored: Vec<(u32, f64)> = bucket.iter().map(|id| (*id, values.get(id).cloned().unwrap_or(0.0))).collect();
+
+      return match direction {
+        SortDirection::Asc => bucket_sort_asc(scored),
+        SortDirection::Desc => bucket_sort_desc(scored)
+      };
     }
   }
+}
+
+fn apply_ranking_rules(index: &Index, query: &str, candidates: Vec<u32>) -> Vec<u32> {
+  let query_words = clean_words(query.to_string());
+  let mut buckets: Vec<Vec<u32>> = vec![candidates];
+
+  for rule in index.ranking_rules.iter() {
+    let mut next_buckets: Vec<Vec<u32>> = Vec::new();
+
+    for bucket in buckets {
+      next_buckets.extend(apply_rule_to_bucket(index, rule, query, &query_words, bucket));
+    }
+
+    buckets = next_buckets;
+  }
 
-  let mut key_score_list: Vec<(u32, f32)> = Vec::new();
+  return buckets.into_iter().flatten().collect();
+}
+
+fn get_key_score_list(index: &Index, query: String) -> Vec<(u32, f32)> {
+  let operation = parse_query(&query);
+  let scores = evaluate_operation(index, &operation);
+
+  let mut token_score_list: Vec<(u32, f32)> = Vec::new();
   for (id, score) in scores {
-    key_score_list.push((id, score));
+    token_score_list.push((id, score));
   }
 
-  if key_score_list.len() == 0 {
+  if token_score_list.len() == 0 {
     return Vec::new();
   }
 
-  key_score_list.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-  let highest = key_score_list[0].1;
-  key_score_list.retain(|x| x.1 >= highest / 2.0);
+  token_score_list.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+  let highest = token_score_list[0].1;
+  let candidates: Vec<(u32, f32)> = token_score_list.iter().cloned().filter(|x| x.1 >= highest / 2.0).collect();
+
+  #[cfg(feature = "parallel")]
+  let mut fuzzy_score_list: Vec<(u32, f32)> = candidates
+    .par_iter()
+    .filter_map(|tuple| {
+      let id = tuple.0;
+      let item = index.items.get(&id).unwrap().clone();
+      let value = parse_json(item);
+      let super_string = extract_fields(&value, &index.fields);
+
+      let mut search = FuzzySearch::new(&query, &super_string, true);
+      search.best_match().map(|fuzzy_match| (id, fuzzy_match.score() as f32))
+    })
+    .collect();
+
+  #[cfg(not(feature = "parallel"))]
+  let mut fuzzy_score_list: Vec<(u32, f32)> = {
+    let mut list: Vec<(u32, f32)> = Vec::new();
+    for tuple in candidates.iter() {
+      let id = tuple.0;
+      let item = index.items.get(&id).unwrap().clone();
+      let value = parse_json(item);
+      let super_string = extract_fields(&value, &index.fields);
+
+      let mut search = FuzzySearch::new(&query, &super_string, true);
+      let fuzzy_match = search.best_match();
+
+      if fuzzy_match.is_some() {
+        let score = fuzzy_match.unwrap().score() as f32;
+        list.push((id.clone(), score));
+      }
+    }
+    list
+  };
 
-  println!("{} candidates", key_score_list.len());
+  fuzzy_score_list.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
 
-  let mut fuzzy_scores: Vec<(u32, f32)> = Vec::new();
-  for tuple in key_score_list.iter_mut() {
-    let id = tuple.0;
-    let item = index.items.get(&id).unwrap().clone();
-    let value = parse_json(item);
-    let super_string = extract_fields(&value, &index.fields);
+  // Reciprocal Rank Fusion: blend the token-weight ranking and the fuzzy
+  // ranking so a document doesn't need to win on both signals to surface.
+  let mut fused_scores: HashMap<u32, f32> = HashMap::new();
 
-    let mut search = FuzzySearch::new(&query, &super_string, true);
-    let fuzzy_match = search.best_match();
-    
-    if fuzzy_match.is_some() {
-      let score = fuzzy_match.unwrap().score() as f32;
-      fuzzy_scores.push(
-        (id.clone(), score)
-      );
+  if index.token_ranking_enabled {
+    for (rank, tuple) in candidates.iter().enumerate() {
+      *fused_scores.entry(tuple.0).or_insert(0.0) += 1.0 / (index.rrf_k + rank as f32 + 1.0);
     }
   }
 
-  fuzzy_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-  let highest = key_score_list[0].1;
-  key_score_list.retain(|x| x.1 >= highest / 4.0);
+  if index.fuzzy_ranking_enabled {
+    for (rank, tuple) in fuzzy_score_list.iter().enumerate() {
+      *fused_scores.entry(tuple.0).or_insert(0.0) += 1.0 / (index.rrf_k + rank as f32 + 1.0);
+    }
+  }
+
+  let mut fused_list: Vec<(u32, f32)> = fused_scores.into_iter().collect();
+  fused_list.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+  if index.ranking_rules.len() > 0 {
+    let ids: Vec<u32> = fused_list.iter().map(|x| x.0).collect();
+    let ranked_ids = apply_ranking_rules(index, &query, ids);
+
+    return ranked_ids.into_iter().enumerate().map(|(rank, id)| (id, -(rank as f32))).collect();
+  }
 
-  return fuzzy_scores;
+  return fused_list;
 }
 
 pub fn search(index: &Index, original_query: String) -> Vec<String> {
@@ -218,9 +776,9 @@ pub fn search(index: &Index, original_query: String) -> Vec<String> {
     }
     return vec;
   }
-  
+
   let mut key_score_list = get_key_score_list(&index, query.to_string());
-  
+
   let mut real_items : Vec<String> = vec![];
   for tuple in key_score_list.iter_mut() {
     let item = index.items.get(&tuple.0).unwrap().clone();
@@ -229,3 +787,129 @@ pub fn search(index: &Index, original_query: String) -> Vec<String> {
 
   return real_items;
 }
+
+pub struct SearchOpts {
+  pub filter: Option<FilterExpr>,
+  pub sort: Option<(String, SortDirection)>,
+  pub facets: Option<Vec<String>>
+}
+
+pub struct SearchResult {
+  pub items: Vec<String>,
+  pub facet_distribution: Option<HashMap<String, HashMap<String, usize>>>
+}
+
+fn filter_eq_ids(index: &Index, field: &str, value: &FilterValue) -> HashSet<u32> {
+  let key = match value {
+    FilterValue::Str(s) => s.clone(),
+    FilterValue::Num(n) => n.to_string()
+  };
+
+  return match index.filter_values.get(field).and_then(|values| values.get(&key)) {
+    Some(ids) => ids.iter().cloned().collect(),
+    None => HashSet::new()
+  };
+}
+
+fn evaluate_filter(index: &Index, expr: &FilterExpr) -> HashSet<u32> {
+  match expr {
+    FilterExpr::Eq(field, value) => filter_eq_ids(index, field, value),
+    FilterExpr::In(field, values) => {
+      let mut result = HashSet::new();
+      for value in values.iter() {
+        result.extend(filter_eq_ids(index, field, value));
+      }
+      return result;
+    },
+    FilterExpr::Gte(field, threshold) => {
+      return match index.sortable_values.get(field) {
+        Some(values) => values.iter().filter(|x| x.1 >= *threshold).map(|x| x.0).collect(),
+        None => HashSet::new()
+      };
+    },
+    FilterExpr::Lte(field, threshold) => {
+      return match index.sortable_values.get(field) {
+        Some(values) => values.iter().filter(|x| x.1 <= *threshold).map(|x| x.0).collect(),
+        None => HashSet::new()
+      };
+    },
+    FilterExpr::And(exprs) => {
+      let mut result: Option<HashSet<u32>> = None;
+
+      for sub_expr in exprs.iter() {
+        let sub_ids = evaluate_filter(index, sub_expr);
+        result = Some(match result {
+          None => sub_ids,
+          Some(prev) => prev.intersection(&sub_ids).cloned().collect()
+        });
+      }
+
+      return result.unwrap_or_else(HashSet::new);
+    },
+    FilterExpr::Or(exprs) => {
+      let mut result = HashSet::new();
+      for sub_expr in exprs.iter() {
+        result.extend(evaluate_filter(index, sub_expr));
+      }
+      return result;
+    }
+  }
+}
+
+pub fn search_with_opts(index: &Index, original_query: String, opts: SearchOpts) -> SearchResult {
+  let query = original_query.trim();
+
+  let mut key_score_list: Vec<(u32, f32)> = if query.len() == 0 {
+    index.items.keys().map(|id| (*id, 0.0)).collect()
+  } else {
+    get_key_score_list(&index, query.to_string())
+  };
+
+  if let Some(filter) = &opts.filter {
+    let allowed = evaluate_filter(index, filter);
+    key_score_list.retain(|x| allowed.contains(&x.0));
+  }
+
+  if let Some((field, direction)) = &opts.sort {
+    let values: HashMap<u32, f64> = index.sortable_values
+      .get(field)
+      .map(|v| v.iter().cloned().collect())
+      .unwrap_or_else(HashMap::new);
+
+    key_score_list.sort_by(|a, b| {
+      let a_val = values.get(&a.0).cloned().unwrap_or(0.0);
+      let b_val = values.get(&b.0).cloned().unwrap_or(0.0);
+      match direction {
+        SortDirection::Asc => a_val.partial_cmp(&b_val).unwrap(),
+        SortDirection::Desc => b_val.partial_cmp(&a_val).unwrap()
+      }
+    });
+  }
+
+  let matched: HashSet<u32> = key_score_list.iter().map(|x| x.0).collect();
+
+  let facet_distribution = opts.facets.map(|fields| {
+    let mut distribution: HashMap<String, HashMap<String, usize>> = HashMap::new();
+
+    for field in fields.iter() {
+      let mut counts: HashMap<String, usize> = HashMap::new();
+
+      if let Some(values) = index.filter_values.get(field) {
+        for (value, ids) in values.iter() {
+          let count = ids.iter().filter(|id| matched.contains(id)).count();
+          if count > 0 {
+            counts.insert(value.clone(), count);
+          }
+        }
+      }
+
+      distribution.insert(field.clone(), counts);
+    }
+
+    distribution
+  });
+
+  let items: Vec<String> = key_score_list.iter().map(|x| index.items.get(&x.0).unwrap().clone()).collect();
+
+  return SearchResult { items, facet_distribution };
+}
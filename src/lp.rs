@@ -0,0 +1,118 @@
+pub fn clean_words(input: String) -> Vec<String> {
+  return input
+    .split_whitespace()
+    .map(|word| word.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase())
+    .filter(|word| word.len() > 0)
+    .collect();
+}
+
+pub fn gramify(input: String) -> Vec<String> {
+  let mut grams: Vec<String> = Vec::new();
+
+  for word in clean_words(input) {
+    let chars: Vec<char> = word.chars().collect();
+
+    if chars.len() < 3 {
+      grams.push(word);
+      continue;
+    }
+
+    for window in chars.windows(3) {
+      grams.push(window.iter().collect());
+    }
+  }
+
+  return grams;
+}
+
+pub fn edit_distance(a: &str, b: &str) -> usize {
+  let a: Vec<char> = a.chars().collect();
+  let b: Vec<char> = b.chars().collect();
+
+  let mut prev_row: Vec<usize> = (0..=b.len()).collect();
+
+  for i in 1..=a.len() {
+    let mut row = vec![i; b.len() + 1];
+
+    for j in 1..=b.len() {
+      let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+      row[j] = (prev_row[j] + 1)
+        .min(row[j - 1] + 1)
+        .min(prev_row[j - 1] + cost);
+    }
+
+    prev_row = row;
+  }
+
+  return prev_row[b.len()];
+}
+
+pub struct LevenshteinAutomaton {
+  word: Vec<char>,
+  max_distance: usize
+}
+
+impl LevenshteinAutomaton {
+  pub fn new(word: &str, max_distance: usize) -> LevenshteinAutomaton {
+    LevenshteinAutomaton {
+      word: word.chars().collect(),
+      max_distance
+    }
+  }
+
+  pub fn distance(&self, candidate: &str) -> Option<usize> {
+    let candidate: Vec<char> = candidate.chars().collect();
+
+    let len_diff = if self.word.len() > candidate.len() {
+      self.word.len() - candidate.len()
+    } else {
+      candidate.len() - self.word.len()
+    };
+
+    if len_diff > self.max_distance {
+      return None;
+    }
+
+    let mut prev_row: Vec<usize> = (0..=candidate.len()).collect();
+
+    for i in 1..=self.word.len() {
+      let mut row = vec![i; candidate.len() + 1];
+      let mut row_min = row[0];
+
+      for j in 1..=candidate.len() {
+        let cost = if self.word[i - 1] == candidate[j - 1] { 0 } else { 1 };
+        row[j] = (prev_row[j] + 1)
+          .min(row[j - 1] + 1)
+          .min(prev_row[j - 1] + cost);
+        row_min = row_min.min(row[j]);
+      }
+
+      if row_min > self.max_distance {
+        return None;
+      }
+
+      prev_row = row;
+    }
+
+    let distance = prev_row[candidate.len()];
+
+    if distance <= self.max_distance {
+      return Some(distance);
+    }
+
+    return None;
+  }
+}
+
+pub fn typo_candidates<'a>(word: &str, keys: impl Iterator<Item = &'a String>, max_distance: usize) -> Vec<(&'a String, usize)> {
+  let automaton = LevenshteinAutomaton::new(word, max_distance);
+  let mut matches: Vec<(&'a String, usize)> = Vec::new();
+
+  for key in keys {
+    if let Some(distance) = automaton.distance(key) {
+      matches.push((key, distance));
+    }
+  }
+
+  return matches;
+}
@@ -0,0 +1,84 @@
+use crate::lp::clean_words;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+  And(Vec<Operation>),
+  Or(Vec<Operation>),
+  Not(Box<Operation>),
+  Term(String),
+  Phrase(Vec<String>)
+}
+
+pub fn parse_query(query: &str) -> Operation {
+  let mut or_groups: Vec<Vec<Operation>> = vec![vec![]];
+  let chars: Vec<char> = query.chars().collect();
+  let mut i = 0;
+
+  while i < chars.len() {
+    let c = chars[i];
+
+    if c.is_whitespace() {
+      i += 1;
+      continue;
+    }
+
+    if c == '"' {
+      let mut j = i + 1;
+      let mut phrase = String::new();
+      while j < chars.len() && chars[j] != '"' {
+        phrase.push(chars[j]);
+        j += 1;
+      }
+
+      let words = clean_words(phrase);
+      if words.len() > 0 {
+        or_groups.last_mut().unwrap().push(Operation::Phrase(words));
+      }
+
+      i = j + 1;
+      continue;
+    }
+
+    let mut j = i;
+    while j < chars.len() && !chars[j].is_whitespace() {
+      j += 1;
+    }
+
+    let word: String = chars[i..j].iter().collect();
+
+    if word == "OR" {
+      or_groups.push(vec![]);
+    } else if word.starts_with('-') && word.len() > 1 {
+      let term = clean_words(word[1..].to_string());
+      if term.len() > 0 {
+        or_groups.last_mut().unwrap().push(Operation::Not(Box::new(Operation::Term(term[0].clone()))));
+      }
+    } else {
+      let term = clean_words(word);
+      if term.len() > 0 {
+        or_groups.last_mut().unwrap().push(Operation::Term(term[0].clone()));
+      }
+    }
+
+    i = j;
+  }
+
+  let and_groups: Vec<Operation> = or_groups.into_iter()
+    .filter(|group| group.len() > 0)
+    .map(|group| if group.len() == 1 {
+      group.into_iter().next().unwrap()
+    } else {
+      Operation::And(group)
+    })
+    .collect();
+
+  if and_groups.len() == 0 {
+    return Operation::And(Vec::new());
+  }
+
+  if and_groups.len() == 1 {
+    return and_groups.into_iter().next().unwrap();
+  }
+
+  return Operation::Or(and_groups);
+}
@@ -0,0 +1,15 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortDirection {
+  Asc,
+  Desc
+}
+
+#[derive(Debug, Clone)]
+pub enum RankingRule {
+  Words,
+  Typo,
+  Proximity,
+  Fuzzy,
+  FieldWeight(String),
+  Sort(String, SortDirection)
+}